@@ -1,12 +1,11 @@
-use crate::field::M;
-use ark_bn254::Fr;
-use ark_ff::{BigInt, PrimeField, Zero};
-use rand::Rng;
+use ark_ff::{BigInteger, PrimeField};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
 use ruint::aliases::U256;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    ops::{BitAnd, Shl, Shr},
+    ops::{Shl, Shr},
 };
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
@@ -30,6 +29,32 @@ where
     a.map_err(serde::de::Error::custom)
 }
 
+/// Convert a field element's canonical big integer representation into a `U256`.
+///
+/// Fields with a modulus wider than 256 bits are truncated; every curve circom
+/// currently supports (bn254, bls12-381, pasta, ...) fits comfortably within 256 bits.
+pub(crate) fn field_to_u256<F: PrimeField>(f: F) -> U256 {
+    let bytes = f.into_bigint().to_bytes_le();
+    let mut buf = [0u8; 32];
+    let len = bytes.len().min(32);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    U256::from_le_bytes(buf)
+}
+
+/// Reduce a `U256` modulo the field order and lift it into `F`.
+pub(crate) fn u256_to_field<F: PrimeField>(v: U256) -> F {
+    F::from_le_bytes_mod_order(&v.to_le_bytes::<32>())
+}
+
+/// The field modulus, as a `U256`, for use in the non-algebraic (integer) evaluators.
+pub(crate) fn modulus_u256<F: PrimeField>() -> U256 {
+    let bytes = F::MODULUS.to_bytes_le();
+    let mut buf = [0u8; 32];
+    let len = bytes.len().min(32);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    U256::from_le_bytes(buf)
+}
+
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Operation {
     Mul,
@@ -46,24 +71,38 @@ pub enum Operation {
     Shl,
     Shr,
     Band,
+    IntDiv,
+    Mod,
+    Pow,
+    Bor,
+    Bxor,
+    /// Bitwise NOT. Unary: only `a` is read.
+    Bnot,
+    /// Logical NOT. Unary: only `a` is read.
+    Lnot,
+    Land,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Node {
+pub enum Node<F: PrimeField> {
     Input(usize),
     Constant(U256),
     #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
-    MontConstant(Fr),
+    MontConstant(F),
     Op(Operation, usize, usize),
 }
 
 impl Operation {
-    pub fn eval(&self, a: U256, b: U256) -> U256 {
+    /// Evaluate on raw (non-Montgomery) integers. `Add`, `Sub`, `Mul`, `MMul`
+    /// and `Pow` are reduced modulo `modulus`; the other ops return their
+    /// integer result unreduced (e.g. `Bnot` of a small value exceeds `modulus`).
+    pub fn eval(&self, a: U256, b: U256, modulus: U256) -> U256 {
         use Operation::*;
         match self {
-            Add => a.add_mod(b, M),
-            Sub => a.add_mod(M - b, M),
-            Mul => a.mul_mod(b, M),
+            Add => a.add_mod(b, modulus),
+            Sub => a.add_mod(modulus - b, modulus),
+            Mul => a.mul_mod(b, modulus),
+            MMul => a.mul_mod(b, modulus),
             Eq => U256::from(a == b),
             Neq => U256::from(a != b),
             Lt => U256::from(a < b),
@@ -74,27 +113,55 @@ impl Operation {
             Shl => compute_shl_uint(a, b),
             Shr => compute_shr_uint(a, b),
             Band => a.bitand(b),
-            _ => unimplemented!("operator {:?} not implemented", self),
+            IntDiv => {
+                if b == U256::ZERO {
+                    U256::ZERO
+                } else {
+                    a / b
+                }
+            }
+            Mod => {
+                if b == U256::ZERO {
+                    U256::ZERO
+                } else {
+                    a % b
+                }
+            }
+            Pow => a.pow_mod(b, modulus),
+            Bor => a.bitor(b),
+            Bxor => a.bitxor(b),
+            Bnot => a.not(),
+            Lnot => U256::from(a == U256::ZERO),
+            Land => U256::from(a != U256::ZERO && b != U256::ZERO),
         }
     }
 
-    pub fn eval_fr(&self, a: Fr, b: Fr) -> Fr {
+    /// Evaluate directly in the field, for any `F: PrimeField`.
+    pub fn eval_fr<F: PrimeField>(&self, a: F, b: F) -> F {
         use Operation::*;
         match self {
             Add => a + b,
             Sub => a - b,
             Mul => a * b,
+            MMul => a * b,
             Eq => (a == b).into(),
             Neq => (a != b).into(),
             Lt => (a < b).into(),
             Gt => (a > b).into(),
             Leq => (a <= b).into(),
             Geq => (a >= b).into(),
-            Lor => (a != Fr::zero() || b != Fr::zero()).into(),
-            Shl => compute_shl_Fr(a, b),
-            Shr => compute_shr_Fr(a, b),
-            Band => compute_bitand_Fr(a, b),
-            _ => unimplemented!("operator {:?} not implemented for Montgomery", self),
+            Lor => (a != F::zero() || b != F::zero()).into(),
+            Shl => compute_shl_fr(a, b),
+            Shr => compute_shr_fr(a, b),
+            Band => compute_bitand_fr(a, b),
+            IntDiv => compute_intdiv_fr(a, b),
+            Mod => compute_mod_fr(a, b),
+            Pow => compute_pow_fr(a, b),
+            Bor => compute_bitor_fr(a, b),
+            Bxor => compute_bitxor_fr(a, b),
+            Bnot => compute_bitnot_fr(a),
+            Lnot => (a == F::zero()).into(),
+            Land => (a != F::zero() && b != F::zero()).into(),
         }
     }
 }
@@ -111,50 +178,60 @@ fn compute_shr_uint(a: U256, b: U256) -> U256 {
     a.shr(ls_limb as usize)
 }
 
-fn u8_to_u64_array(input: &[u8]) -> [u64; 4] {
-    // Check that the length of the input array is exactly 32 bytes
-    assert!(input.len() == 32, "Input length must be exactly 32 bytes");
+pub fn compute_shl_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(compute_shl_uint(a, b))
+}
 
-    // Create an array to hold the converted values
-    let mut output = [0u64; 4];
+pub fn compute_shr_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(compute_shr_uint(a, b))
+}
 
-    // Iterate over chunks of 8 bytes and convert them to u64
-    for (i, chunk) in input.chunks_exact(8).enumerate() {
-        let mut array = [0u8; 8];
-        array.copy_from_slice(chunk);
-        output[i] = u64::from_le_bytes(array);
-    }
+pub fn compute_bitand_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(a.bitand(b))
+}
 
-    output
+pub fn compute_bitor_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(a.bitor(b))
 }
 
-pub fn compute_shl_Fr(a: Fr, b: Fr) -> Fr {
-    // convert Fr to U256
-    let a: U256 = a.into();
-    let b: U256 = b.into();
-    let result = compute_shl_uint(a, b);
-    let bytes: [u8; 64] = result.to_le_bytes();
-    Fr::from(BigInt::new(u8_to_u64_array(&bytes)))
+pub fn compute_bitxor_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(a.bitxor(b))
 }
 
-pub fn compute_shr_Fr(a: Fr, b: Fr) -> Fr {
-    let a: U256 = a.into();
-    let b: U256 = b.into();
-    let result = compute_shr_uint(a, b);
-    let bytes: [u8; 64] = result.to_le_bytes();
-    Fr::from(BigInt::new(u8_to_u64_array(&bytes)))
+pub fn compute_bitnot_fr<F: PrimeField>(a: F) -> F {
+    u256_to_field(field_to_u256(a).not())
 }
 
-pub fn compute_bitand_Fr(a: Fr, b: Fr) -> Fr {
-    let a: U256 = a.into();
-    let b: U256 = b.into();
-    let result = a.bitand(b);
-    let bytes: [u8; 64] = result.to_le_bytes();
-    Fr::from(BigInt::new(u8_to_u64_array(&bytes)))
+pub fn compute_intdiv_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(if b == U256::ZERO { U256::ZERO } else { a / b })
+}
+
+pub fn compute_mod_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(if b == U256::ZERO { U256::ZERO } else { a % b })
+}
+
+pub fn compute_pow_fr<F: PrimeField>(a: F, b: F) -> F {
+    let a = field_to_u256(a);
+    let b = field_to_u256(b);
+    u256_to_field(a.pow_mod(b, modulus_u256::<F>()))
 }
 
 /// All references must be backwards.
-fn assert_valid(nodes: &[Node]) {
+fn assert_valid<F: PrimeField>(nodes: &[Node<F>]) {
     for (i, &node) in nodes.iter().enumerate() {
         if let Node::Op(_, a, b) = node {
             assert!(a < i);
@@ -163,25 +240,37 @@ fn assert_valid(nodes: &[Node]) {
     }
 }
 
-pub fn optimize(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
+/// Run the full optimization pipeline.
+///
+/// `k` sets the soundness of the probabilistic `value_numbering` and
+/// `constants` passes (see their docs for what each does with it); `seed`,
+/// if given, makes the whole pipeline deterministic and reproducible across
+/// runs. `constants` needs at least 2 rounds to mean anything, so its floor
+/// is raised here rather than requiring every caller to know that.
+pub fn optimize<F: PrimeField>(
+    nodes: &mut Vec<Node<F>>,
+    outputs: &mut [usize],
+    k: usize,
+    seed: Option<u64>,
+) {
     tree_shake(nodes, outputs);
     propagate(nodes);
-    value_numbering(nodes, outputs);
-    constants(nodes);
+    value_numbering(nodes, outputs, k, seed);
+    constants(nodes, k.max(2), seed);
     tree_shake(nodes, outputs);
     montgomery_form(nodes);
 }
 
-pub fn evaluate(nodes: &[Node], inputs: &[U256], outputs: &[usize]) -> Vec<U256> {
+pub fn evaluate<F: PrimeField>(nodes: &[Node<F>], inputs: &[U256], outputs: &[usize]) -> Vec<U256> {
     // assert_valid(nodes);
 
     // Evaluate the graph.
     let mut values = Vec::with_capacity(nodes.len());
     for (_, &node) in nodes.iter().enumerate() {
         let value = match node {
-            Node::Constant(c) => Fr::new(c.into()),
+            Node::Constant(c) => u256_to_field::<F>(c),
             Node::MontConstant(c) => c,
-            Node::Input(i) => Fr::new(inputs[i].into()),
+            Node::Input(i) => u256_to_field::<F>(inputs[i]),
             Node::Op(op, a, b) => op.eval_fr(values[a], values[b]),
         };
         values.push(value);
@@ -190,30 +279,139 @@ pub fn evaluate(nodes: &[Node], inputs: &[U256], outputs: &[usize]) -> Vec<U256>
     // Convert from Montgomery form and return the outputs.
     let mut out = vec![U256::ZERO; outputs.len()];
     for i in 0..outputs.len() {
-        out[i] = U256::try_from(values[outputs[i]].into_bigint()).unwrap();
+        out[i] = field_to_u256(values[outputs[i]]);
+    }
+
+    out
+}
+
+/// Compute the topological "level" of each node: `0` for `Input`/`Constant`/
+/// `MontConstant`, and `1 + max(level[a], level[b])` for each `Op`. Because
+/// all `Op` references are backwards, a node's level only ever depends on
+/// levels already computed.
+fn compute_levels<F: PrimeField>(nodes: &[Node<F>]) -> Vec<usize> {
+    let mut levels: Vec<usize> = Vec::with_capacity(nodes.len());
+    for &node in nodes {
+        let level = match node {
+            Node::Op(_, a, b) => 1 + levels[a].max(levels[b]),
+            _ => 0,
+        };
+        levels.push(level);
+    }
+    levels
+}
+
+/// Evaluate the graph in parallel, exploiting independent regions of the graph.
+///
+/// Nodes are grouped by topological level (see `compute_levels`): within a
+/// level no node depends on another, since every `Op` only reads strictly
+/// lower indices. Each level is therefore evaluated with all of its nodes
+/// computed concurrently via rayon before moving to the next level. Produces
+/// identical output to `evaluate`.
+pub fn evaluate_par<F: PrimeField>(
+    nodes: &[Node<F>],
+    inputs: &[U256],
+    outputs: &[usize],
+) -> Vec<U256> {
+    let levels = compute_levels(nodes);
+    let max_level = levels.iter().copied().max().unwrap_or(0);
+
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); max_level + 1];
+    for (i, &level) in levels.iter().enumerate() {
+        buckets[level].push(i);
+    }
+
+    let mut values: Vec<F> = vec![F::zero(); nodes.len()];
+    for bucket in &buckets {
+        let computed: Vec<(usize, F)> = bucket
+            .par_iter()
+            .map(|&i| {
+                let value = match nodes[i] {
+                    Node::Constant(c) => u256_to_field::<F>(c),
+                    Node::MontConstant(c) => c,
+                    Node::Input(idx) => u256_to_field::<F>(inputs[idx]),
+                    Node::Op(op, a, b) => op.eval_fr(values[a], values[b]),
+                };
+                (i, value)
+            })
+            .collect();
+        for (i, value) in computed {
+            values[i] = value;
+        }
+    }
+
+    // Convert from Montgomery form and return the outputs.
+    let mut out = vec![U256::ZERO; outputs.len()];
+    for i in 0..outputs.len() {
+        out[i] = field_to_u256(values[outputs[i]]);
+    }
+
+    out
+}
+
+/// Evaluate the graph once over a batch of input assignments.
+///
+/// For each node a lane-wise `Vec<F>` of width `inputs.len()` is kept instead
+/// of a single value, so the graph traversal, cache misses, and Montgomery
+/// output conversion are all amortized across the whole batch rather than
+/// repeated per witness. Returns one output vector per input assignment, in
+/// the same order as `inputs`.
+pub fn evaluate_batch<F: PrimeField>(
+    nodes: &[Node<F>],
+    inputs: &[&[U256]],
+    outputs: &[usize],
+) -> Vec<Vec<U256>> {
+    let batch_size = inputs.len();
+
+    let mut values: Vec<Vec<F>> = Vec::with_capacity(nodes.len());
+    for &node in nodes {
+        let value: Vec<F> = match node {
+            Node::Constant(c) => vec![u256_to_field::<F>(c); batch_size],
+            Node::MontConstant(c) => vec![c; batch_size],
+            Node::Input(i) => inputs
+                .iter()
+                .map(|assignment| u256_to_field::<F>(assignment[i]))
+                .collect(),
+            Node::Op(op, a, b) => (0..batch_size)
+                .map(|lane| op.eval_fr(values[a][lane], values[b][lane]))
+                .collect(),
+        };
+        values.push(value);
+    }
+
+    // Convert from Montgomery form and return the outputs, one vector per lane.
+    let mut out = vec![vec![U256::ZERO; outputs.len()]; batch_size];
+    for (oi, &node_idx) in outputs.iter().enumerate() {
+        for (lane, out_row) in out.iter_mut().enumerate() {
+            out_row[oi] = field_to_u256(values[node_idx][lane]);
+        }
     }
 
     out
 }
 
 /// Constant propagation
-pub fn propagate(nodes: &mut [Node]) {
+pub fn propagate<F: PrimeField>(nodes: &mut [Node<F>]) {
     assert_valid(nodes);
+    let modulus = modulus_u256::<F>();
     let mut constants = 0_usize;
     for i in 0..nodes.len() {
         if let Node::Op(op, a, b) = nodes[i] {
             if let (Node::Constant(va), Node::Constant(vb)) = (nodes[a], nodes[b]) {
-                nodes[i] = Node::Constant(op.eval(va, vb));
+                nodes[i] = Node::Constant(op.eval(va, vb, modulus));
                 constants += 1;
             } else if a == b {
                 // Not constant but equal
                 use Operation::*;
-                if let Some(c) = match op {
-                    Eq | Leq | Geq => Some(true),
-                    Neq | Lt | Gt => Some(false),
+                let folded = match op {
+                    Eq | Leq | Geq => Some(U256::from(true)),
+                    Neq | Lt | Gt => Some(U256::from(false)),
+                    // a mod a and a xor a are always zero, regardless of a's value.
+                    Mod | Bxor => Some(U256::ZERO),
                     _ => None,
-                } {
-                    nodes[i] = Node::Constant(U256::from(c));
+                };
+                if let Some(c) = folded {
+                    nodes[i] = Node::Constant(c);
                     constants += 1;
                 }
             }
@@ -224,7 +422,7 @@ pub fn propagate(nodes: &mut [Node]) {
 }
 
 /// Remove unused nodes
-pub fn tree_shake(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
+pub fn tree_shake<F: PrimeField>(nodes: &mut Vec<Node<F>>, outputs: &mut [usize]) {
     assert_valid(nodes);
 
     // Mark all nodes that are used.
@@ -277,9 +475,17 @@ pub fn tree_shake(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
     eprintln!("Removed {removed} unused nodes");
 }
 
-/// Randomly evaluate the graph
-fn random_eval(nodes: &mut Vec<Node>) -> Vec<U256> {
-    let mut rng = rand::thread_rng();
+/// Build the RNG that drives a round of `random_eval`, seeded if asked.
+fn make_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(rand::thread_rng()).expect("failed to seed RNG"),
+    }
+}
+
+/// Randomly evaluate the graph. Each call draws a fresh assignment from `rng`.
+fn random_eval<F: PrimeField>(nodes: &mut Vec<Node<F>>, rng: &mut StdRng) -> Vec<U256> {
+    let modulus = modulus_u256::<F>();
     let mut values = Vec::with_capacity(nodes.len());
     let mut inputs = HashMap::new();
     let mut prfs = HashMap::new();
@@ -289,42 +495,56 @@ fn random_eval(nodes: &mut Vec<Node>) -> Vec<U256> {
             // Constants evaluate to themselves
             Node::Constant(c) => *c,
 
-            Node::MontConstant(c) => unimplemented!("should not be used"),
+            Node::MontConstant(_) => unimplemented!("should not be used"),
 
             // Algebraic Ops are evaluated directly
             // Since the field is large, by Swartz-Zippel if
             // two values are the same then they are likely algebraically equal.
-            Node::Op(op @ (Add | Sub | Mul), a, b) => op.eval(values[*a], values[*b]),
+            Node::Op(op @ (Add | Sub | Mul | MMul), a, b) => {
+                op.eval(values[*a], values[*b], modulus)
+            }
 
             // Input and non-algebraic ops are random functions
-            // TODO: https://github.com/recmo/uint/issues/95 and use .gen_range(..M)
-            Node::Input(i) => *inputs.entry(*i).or_insert_with(|| rng.gen::<U256>() % M),
+            // TODO: https://github.com/recmo/uint/issues/95 and use .gen_range(..modulus)
+            Node::Input(i) => *inputs
+                .entry(*i)
+                .or_insert_with(|| rng.gen::<U256>() % modulus),
             Node::Op(op, a, b) => *prfs
                 .entry((*op, values[*a], values[*b]))
-                .or_insert_with(|| rng.gen::<U256>() % M),
+                .or_insert_with(|| rng.gen::<U256>() % modulus),
         };
         values.push(value);
     }
     values
 }
 
-/// Value numbering
-pub fn value_numbering(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
+/// Value numbering: merge nodes that evaluate to the same value under `k`
+/// independent random assignments, keeping the first index of each group.
+/// `k == 1` reproduces the original single-round behavior.
+pub fn value_numbering<F: PrimeField>(
+    nodes: &mut Vec<Node<F>>,
+    outputs: &mut [usize],
+    k: usize,
+    seed: Option<u64>,
+) {
     assert_valid(nodes);
+    assert!(k >= 1, "soundness parameter k must be at least 1");
 
-    // Evaluate the graph in random field elements.
-    let values = random_eval(nodes);
+    // Evaluate the graph in k independent random assignments.
+    let mut rng = make_rng(seed);
+    let rounds: Vec<Vec<U256>> = (0..k).map(|_| random_eval(nodes, &mut rng)).collect();
+    let signature = |i: usize| -> Vec<U256> { rounds.iter().map(|round| round[i]).collect() };
 
-    // Find all nodes with the same value.
-    let mut value_map = HashMap::new();
-    for (i, &value) in values.iter().enumerate() {
-        value_map.entry(value).or_insert_with(Vec::new).push(i);
+    // Find all nodes whose values agree in every round.
+    let mut value_map: HashMap<Vec<U256>, Vec<usize>> = HashMap::new();
+    for i in 0..nodes.len() {
+        value_map.entry(signature(i)).or_insert_with(Vec::new).push(i);
     }
 
     // For nodes that are the same, pick the first index.
     let mut renumber = Vec::with_capacity(nodes.len());
-    for value in values {
-        renumber.push(value_map[&value][0]);
+    for i in 0..nodes.len() {
+        renumber.push(value_map[&signature(i)][0]);
     }
 
     // Renumber references.
@@ -341,22 +561,26 @@ pub fn value_numbering(nodes: &mut Vec<Node>, outputs: &mut [usize]) {
     eprintln!("Global value numbering applied");
 }
 
-/// Probabilistic constant determination
-pub fn constants(nodes: &mut Vec<Node>) {
+/// Probabilistic constant determination: fold a node to a constant if its
+/// value is stable across `k` independent random assignments. `k` must be at
+/// least 2 — with a single round every node trivially "agrees with itself".
+pub fn constants<F: PrimeField>(nodes: &mut Vec<Node<F>>, k: usize, seed: Option<u64>) {
     assert_valid(nodes);
+    assert!(k >= 2, "soundness parameter k must be at least 2");
 
-    // Evaluate the graph in random field elements.
-    let values_a = random_eval(nodes);
-    let values_b = random_eval(nodes);
+    // Evaluate the graph in k independent random assignments.
+    let mut rng = make_rng(seed);
+    let rounds: Vec<Vec<U256>> = (0..k).map(|_| random_eval(nodes, &mut rng)).collect();
 
-    // Find all nodes with the same value.
+    // Find all nodes whose value agrees in every round.
     let mut constants = 0;
     for i in 0..nodes.len() {
         if let Node::Constant(_) = nodes[i] {
             continue;
         }
-        if values_a[i] == values_b[i] {
-            nodes[i] = Node::Constant(values_a[i]);
+        let first = rounds[0][i];
+        if rounds.iter().all(|round| round[i] == first) {
+            nodes[i] = Node::Constant(first);
             constants += 1;
         }
     }
@@ -364,11 +588,11 @@ pub fn constants(nodes: &mut Vec<Node>) {
 }
 
 /// Convert to Montgomery form
-pub fn montgomery_form(nodes: &mut [Node]) {
+pub fn montgomery_form<F: PrimeField>(nodes: &mut [Node<F>]) {
     for node in nodes.iter_mut() {
         use Node::*;
         match node {
-            Constant(c) => *node = MontConstant(Fr::new((*c).into())),
+            Constant(c) => *node = MontConstant(u256_to_field::<F>(*c)),
             MontConstant(..) => (),
             Input(..) => (),
             Op(..) => (),
@@ -376,3 +600,55 @@ pub fn montgomery_form(nodes: &mut [Node]) {
     }
     eprintln!("Converted to Montgomery form");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+
+    #[test]
+    fn evaluate_par_matches_evaluate() {
+        // out0 = (in0 + in1) * in2, out1 = in0 * in1
+        let nodes: Vec<Node<Fr>> = vec![
+            Node::Input(0),
+            Node::Input(1),
+            Node::Input(2),
+            Node::Op(Operation::Add, 0, 1),
+            Node::Op(Operation::Mul, 3, 2),
+            Node::Op(Operation::Mul, 0, 1),
+        ];
+        let outputs = [4, 5];
+        let inputs = [U256::from(3), U256::from(5), U256::from(7)];
+
+        assert_eq!(
+            evaluate(&nodes, &inputs, &outputs),
+            evaluate_par(&nodes, &inputs, &outputs),
+        );
+    }
+
+    #[test]
+    fn evaluate_batch_matches_scalar_evaluate() {
+        // out0 = in0 + in1, out1 = in0 * in1
+        let nodes: Vec<Node<Fr>> = vec![
+            Node::Input(0),
+            Node::Input(1),
+            Node::Op(Operation::Add, 0, 1),
+            Node::Op(Operation::Mul, 0, 1),
+        ];
+        let outputs = [2, 3];
+
+        let batch_inputs: Vec<Vec<U256>> = vec![
+            vec![U256::from(3), U256::from(5)],
+            vec![U256::from(7), U256::from(11)],
+            vec![U256::from(1), U256::from(2)],
+        ];
+        let refs: Vec<&[U256]> = batch_inputs.iter().map(|v| v.as_slice()).collect();
+
+        let batched = evaluate_batch(&nodes, &refs, &outputs);
+
+        for (lane, input) in batch_inputs.iter().enumerate() {
+            let scalar = evaluate(&nodes, input, &outputs);
+            assert_eq!(batched[lane], scalar);
+        }
+    }
+}
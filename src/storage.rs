@@ -0,0 +1,475 @@
+//! Versioned compact binary format for persisting compiled witness graphs.
+//!
+//! Unlike the per-field `ark_se`/`ark_de` serde hooks on `Node`, this format
+//! is self-describing: a magic+version header identifies the file and the
+//! curve it was compiled for, followed by a tightly packed node table with
+//! fixed-width little-endian operands. `MappedGraph` opens that table via
+//! `mmap` and parses nodes lazily, so large precompiled graphs can be opened
+//! without eagerly building a `Vec<Node<F>>`.
+
+use crate::graph::{field_to_u256, modulus_u256, u256_to_field, Node, Operation};
+use ark_ff::PrimeField;
+use memmap2::Mmap;
+use ruint::aliases::U256;
+use std::{
+    fs::File,
+    io::{self, Error, ErrorKind, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+const MAGIC: [u8; 8] = *b"CWGRAPH\0";
+const CURRENT_VERSION: u32 = 1;
+
+const TAG_INPUT: u8 = 0;
+const TAG_CONSTANT: u8 = 1;
+const TAG_MONT_CONSTANT: u8 = 2;
+const TAG_OP: u8 = 3;
+
+fn operation_to_tag(op: Operation) -> u8 {
+    use Operation::*;
+    match op {
+        Mul => 0,
+        MMul => 1,
+        Add => 2,
+        Sub => 3,
+        Eq => 4,
+        Neq => 5,
+        Lt => 6,
+        Gt => 7,
+        Leq => 8,
+        Geq => 9,
+        Lor => 10,
+        Shl => 11,
+        Shr => 12,
+        Band => 13,
+        IntDiv => 14,
+        Mod => 15,
+        Pow => 16,
+        Bor => 17,
+        Bxor => 18,
+        Bnot => 19,
+        Lnot => 20,
+        Land => 21,
+    }
+}
+
+fn tag_to_operation(tag: u8) -> io::Result<Operation> {
+    use Operation::*;
+    Ok(match tag {
+        0 => Mul,
+        1 => MMul,
+        2 => Add,
+        3 => Sub,
+        4 => Eq,
+        5 => Neq,
+        6 => Lt,
+        7 => Gt,
+        8 => Leq,
+        9 => Geq,
+        10 => Lor,
+        11 => Shl,
+        12 => Shr,
+        13 => Band,
+        14 => IntDiv,
+        15 => Mod,
+        16 => Pow,
+        17 => Bor,
+        18 => Bxor,
+        19 => Bnot,
+        20 => Lnot,
+        21 => Land,
+        _ => return Err(Error::new(ErrorKind::InvalidData, "unknown operation tag")),
+    })
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+/// The smallest possible on-disk encoding of a node: a `TAG_INPUT` byte
+/// followed by an 8-byte index. Used to reject a bogus `num_nodes` before it
+/// reaches `Vec::with_capacity`.
+const MIN_NODE_SIZE: u64 = 9;
+
+/// Check that `count` items of `item_size` bytes each actually fit in
+/// `remaining` bytes, so a corrupted or truncated `count` field fails with an
+/// `io::Error` instead of an allocation-failure abort in `Vec::with_capacity`.
+fn check_count(count: u64, item_size: u64, remaining: usize) -> io::Result<()> {
+    let needed = count
+        .checked_mul(item_size)
+        .ok_or_else(|| invalid_data("truncated graph: count overflow"))?;
+    if needed > remaining as u64 {
+        return Err(invalid_data("truncated graph: count exceeds remaining bytes"));
+    }
+    Ok(())
+}
+
+/// Write a compiled graph as a self-describing binary blob.
+pub fn serialize_graph<F: PrimeField>(nodes: &[Node<F>], outputs: &[usize]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&MAGIC);
+    buf.extend_from_slice(&CURRENT_VERSION.to_le_bytes());
+    buf.extend_from_slice(&modulus_u256::<F>().to_le_bytes::<32>());
+
+    buf.extend_from_slice(&(outputs.len() as u64).to_le_bytes());
+    for &output in outputs {
+        buf.extend_from_slice(&(output as u64).to_le_bytes());
+    }
+
+    buf.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+    for &node in nodes {
+        write_node(&mut buf, node);
+    }
+
+    buf
+}
+
+fn write_node<F: PrimeField>(buf: &mut Vec<u8>, node: Node<F>) {
+    match node {
+        Node::Input(i) => {
+            buf.push(TAG_INPUT);
+            buf.extend_from_slice(&(i as u64).to_le_bytes());
+        }
+        Node::Constant(c) => {
+            buf.push(TAG_CONSTANT);
+            buf.extend_from_slice(&c.to_le_bytes::<32>());
+        }
+        Node::MontConstant(c) => {
+            buf.push(TAG_MONT_CONSTANT);
+            buf.extend_from_slice(&field_to_u256(c).to_le_bytes::<32>());
+        }
+        Node::Op(op, a, b) => {
+            buf.push(TAG_OP);
+            buf.push(operation_to_tag(op));
+            buf.extend_from_slice(&(a as u64).to_le_bytes());
+            buf.extend_from_slice(&(b as u64).to_le_bytes());
+        }
+    }
+}
+
+/// Parsed, validated header: curve check already performed, payload offsets ready to read.
+struct Header {
+    outputs: Vec<usize>,
+    nodes_offset: usize,
+    num_nodes: u64,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    let slice = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| invalid_data("truncated graph: header"))?;
+    Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> io::Result<u64> {
+    let slice = bytes
+        .get(offset..offset + 8)
+        .ok_or_else(|| invalid_data("truncated graph: expected 8-byte field"))?;
+    Ok(u64::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn parse_header<F: PrimeField>(bytes: &[u8]) -> io::Result<Header> {
+    if bytes.len() < MAGIC.len() + 4 + 32 + 8 {
+        return Err(invalid_data("truncated graph: header"));
+    }
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(invalid_data("not a circom-witness-rs graph file"));
+    }
+
+    let version = read_u32(bytes, MAGIC.len())?;
+    if version != CURRENT_VERSION {
+        return Err(invalid_data(&format!(
+            "unsupported graph format version {version}, expected {CURRENT_VERSION}"
+        )));
+    }
+
+    let modulus_offset = MAGIC.len() + 4;
+    let modulus_bytes: [u8; 32] = bytes[modulus_offset..modulus_offset + 32]
+        .try_into()
+        .unwrap();
+    if U256::from_le_bytes(modulus_bytes) != modulus_u256::<F>() {
+        return Err(invalid_data(
+            "graph was compiled for a different curve than the requested field type",
+        ));
+    }
+
+    let mut offset = modulus_offset + 32;
+    let num_outputs = read_u64(bytes, offset)?;
+    offset += 8;
+    check_count(num_outputs, 8, bytes.len() - offset)?;
+
+    let mut outputs = Vec::with_capacity(num_outputs as usize);
+    for _ in 0..num_outputs {
+        outputs.push(read_u64(bytes, offset)? as usize);
+        offset += 8;
+    }
+
+    let num_nodes = read_u64(bytes, offset)?;
+    offset += 8;
+    check_count(num_nodes, MIN_NODE_SIZE, bytes.len() - offset)?;
+
+    Ok(Header {
+        outputs,
+        nodes_offset: offset,
+        num_nodes,
+    })
+}
+
+/// Read one node starting at `bytes[0]`, returning it along with the remaining bytes.
+fn read_node<F: PrimeField>(bytes: &[u8]) -> io::Result<(Node<F>, &[u8])> {
+    let (&tag, rest) = bytes
+        .split_first()
+        .ok_or_else(|| invalid_data("truncated graph: node tag"))?;
+    match tag {
+        TAG_INPUT => {
+            let i = read_u64(rest, 0)?;
+            Ok((Node::Input(i as usize), &rest[8..]))
+        }
+        TAG_CONSTANT => {
+            let bytes: [u8; 32] = rest
+                .get(..32)
+                .ok_or_else(|| invalid_data("truncated graph: constant"))?
+                .try_into()
+                .unwrap();
+            Ok((Node::Constant(U256::from_le_bytes(bytes)), &rest[32..]))
+        }
+        TAG_MONT_CONSTANT => {
+            let bytes: [u8; 32] = rest
+                .get(..32)
+                .ok_or_else(|| invalid_data("truncated graph: mont constant"))?
+                .try_into()
+                .unwrap();
+            let value = u256_to_field::<F>(U256::from_le_bytes(bytes));
+            Ok((Node::MontConstant(value), &rest[32..]))
+        }
+        TAG_OP => {
+            let (&op_tag, rest) = rest
+                .split_first()
+                .ok_or_else(|| invalid_data("truncated graph: op tag"))?;
+            let op = tag_to_operation(op_tag)?;
+            let a = read_u64(rest, 0)? as usize;
+            let b = read_u64(rest, 8)? as usize;
+            Ok((Node::Op(op, a, b), &rest[16..]))
+        }
+        _ => Err(invalid_data("unknown node tag")),
+    }
+}
+
+/// Parse a full graph out of an in-memory buffer (e.g. a file already read into a `Vec<u8>`).
+pub fn deserialize_graph<F: PrimeField>(bytes: &[u8]) -> io::Result<(Vec<Node<F>>, Vec<usize>)> {
+    let header = parse_header::<F>(bytes)?;
+    let mut rest = &bytes[header.nodes_offset..];
+    let mut nodes = Vec::with_capacity(header.num_nodes as usize);
+    for _ in 0..header.num_nodes {
+        let (node, tail) = read_node::<F>(rest)?;
+        nodes.push(node);
+        rest = tail;
+    }
+    Ok((nodes, header.outputs))
+}
+
+/// A compiled graph opened via `mmap`, parsing nodes lazily instead of
+/// eagerly materializing a `Vec<Node<F>>`.
+pub struct MappedGraph<F: PrimeField> {
+    mmap: Mmap,
+    nodes_offset: usize,
+    num_nodes: u64,
+    outputs: Vec<usize>,
+    _field: PhantomData<F>,
+}
+
+impl<F: PrimeField> MappedGraph<F> {
+    /// Memory-map `path` and validate its header. Rejects files with the
+    /// wrong magic, an unsupported version, or a curve id that does not
+    /// match `F`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the file is treated as immutable for the lifetime of the
+        // mapping; concurrent external writers would be a caller error, not
+        // something this type can prevent.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = parse_header::<F>(&mmap)?;
+        Ok(Self {
+            mmap,
+            nodes_offset: header.nodes_offset,
+            num_nodes: header.num_nodes,
+            outputs: header.outputs,
+            _field: PhantomData,
+        })
+    }
+
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_nodes as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_nodes == 0
+    }
+
+    /// Iterate over the graph's nodes, parsing each one lazily from the mapped bytes.
+    pub fn iter(&self) -> MappedGraphIter<'_, F> {
+        MappedGraphIter {
+            bytes: &self.mmap[self.nodes_offset..],
+            remaining: self.num_nodes,
+            _field: PhantomData,
+        }
+    }
+
+    /// Parse the whole mapped graph into an owned `Vec<Node<F>>`.
+    pub fn to_vec(&self) -> io::Result<Vec<Node<F>>> {
+        self.iter().collect()
+    }
+}
+
+pub struct MappedGraphIter<'a, F: PrimeField> {
+    bytes: &'a [u8],
+    remaining: u64,
+    _field: PhantomData<F>,
+}
+
+impl<'a, F: PrimeField> Iterator for MappedGraphIter<'a, F> {
+    type Item = io::Result<Node<F>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match read_node::<F>(self.bytes) {
+            Ok((node, rest)) => {
+                self.bytes = rest;
+                self.remaining -= 1;
+                Some(Ok(node))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Write a serialized graph out to `path`.
+pub fn write_graph_file<F: PrimeField>(
+    path: impl AsRef<Path>,
+    nodes: &[Node<F>],
+    outputs: &[usize],
+) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(&serialize_graph(nodes, outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn sample_graph() -> (Vec<Node<Fr>>, Vec<usize>) {
+        // out0 = ((in0 + in1) * in2) & 7
+        let nodes = vec![
+            Node::Input(0),
+            Node::Input(1),
+            Node::Input(2),
+            Node::Constant(U256::from(7)),
+            Node::Op(Operation::Add, 0, 1),
+            Node::Op(Operation::Mul, 4, 2),
+            Node::Op(Operation::Band, 5, 3),
+        ];
+        let outputs = vec![6];
+        (nodes, outputs)
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "cw-graph-test-{}-{}-{}.bin",
+            std::process::id(),
+            id,
+            name
+        ))
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let (nodes, outputs) = sample_graph();
+        let bytes = serialize_graph(&nodes, &outputs);
+        let (got_nodes, got_outputs) = deserialize_graph::<Fr>(&bytes).unwrap();
+        assert_eq!(got_nodes, nodes);
+        assert_eq!(got_outputs, outputs);
+    }
+
+    #[test]
+    fn round_trips_through_mapped_file() {
+        let (nodes, outputs) = sample_graph();
+        let path = temp_path("mapped");
+        write_graph_file(&path, &nodes, &outputs).unwrap();
+
+        let mapped = MappedGraph::<Fr>::open(&path).unwrap();
+        assert_eq!(mapped.outputs(), outputs.as_slice());
+        assert_eq!(mapped.len(), nodes.len());
+        assert_eq!(mapped.to_vec().unwrap(), nodes);
+
+        let iterated: Vec<Node<Fr>> = mapped.iter().collect::<io::Result<_>>().unwrap();
+        assert_eq!(iterated, nodes);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let (nodes, outputs) = sample_graph();
+        let mut bytes = serialize_graph(&nodes, &outputs);
+        bytes[0] = !bytes[0];
+        assert!(deserialize_graph::<Fr>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_version_mismatch() {
+        let (nodes, outputs) = sample_graph();
+        let mut bytes = serialize_graph(&nodes, &outputs);
+        let version_offset = MAGIC.len();
+        bytes[version_offset..version_offset + 4]
+            .copy_from_slice(&(CURRENT_VERSION + 1).to_le_bytes());
+        assert!(deserialize_graph::<Fr>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_curve_mismatch() {
+        use ark_bls12_381::Fr as Bls12_381Fr;
+
+        let (nodes, outputs) = sample_graph();
+        let bytes = serialize_graph(&nodes, &outputs);
+        assert!(deserialize_graph::<Bls12_381Fr>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_counts_without_aborting() {
+        let (nodes, outputs) = sample_graph();
+        let mut bytes = serialize_graph(&nodes, &outputs);
+
+        // Drop the node table and overwrite num_nodes (the 8 bytes right
+        // before it) with a huge count, simulating a truncated/corrupted
+        // blob. This must return an `io::Error`, not abort by overflowing
+        // `Vec::with_capacity`.
+        let header_len = bytes.len() - nodes_byte_len(&nodes);
+        bytes.truncate(header_len);
+        let count_offset = header_len - 8;
+        bytes[count_offset..header_len].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        assert!(deserialize_graph::<Fr>(&bytes).is_err());
+    }
+
+    fn nodes_byte_len(nodes: &[Node<Fr>]) -> usize {
+        let mut buf = Vec::new();
+        for &node in nodes {
+            write_node(&mut buf, node);
+        }
+        buf.len()
+    }
+}